@@ -0,0 +1,77 @@
+//! Defaults for launch options, loaded from `config.toml` in the app config
+//! directory. Values found here are used whenever the corresponding CLI flag
+//! was not given; CLI flags always win.
+
+use std::fs;
+
+#[derive(Default, Clone, Deserialize)]
+pub struct Config {
+    pub nvim_bin_path: Option<String>,
+    pub nvim_args: Option<Vec<String>>,
+    pub timeout: Option<u64>,
+    pub enable_swap: Option<bool>,
+    pub server: Option<String>,
+    pub fork: Option<bool>,
+}
+
+impl Config {
+    pub fn load() -> Config {
+        dirs::get_app_config_dir()
+            .and_then(|dir| fs::read_to_string(dir.join("config.toml")).ok())
+            .map(|content| Config::from_toml(&content))
+            .unwrap_or_default()
+    }
+
+    fn from_toml(content: &str) -> Config {
+        match toml::from_str(content) {
+            Ok(config) => config,
+            Err(err) => {
+                error!("Can't parse config.toml: {}", err);
+                Config::default()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_all_fields() {
+        let config = Config::from_toml(
+            r#"
+            nvim_bin_path = "/usr/bin/nvim"
+            nvim_args = ["--clean"]
+            timeout = 30
+            enable_swap = true
+            server = "127.0.0.1:6666"
+            fork = false
+            "#,
+        );
+
+        assert_eq!(config.nvim_bin_path, Some("/usr/bin/nvim".to_owned()));
+        assert_eq!(config.nvim_args, Some(vec!["--clean".to_owned()]));
+        assert_eq!(config.timeout, Some(30));
+        assert_eq!(config.enable_swap, Some(true));
+        assert_eq!(config.server, Some("127.0.0.1:6666".to_owned()));
+        assert_eq!(config.fork, Some(false));
+    }
+
+    #[test]
+    fn falls_back_to_defaults_on_invalid_toml() {
+        let config = Config::from_toml("not = [valid");
+        assert!(config.nvim_bin_path.is_none());
+    }
+
+    #[test]
+    fn empty_file_yields_all_none() {
+        let config = Config::from_toml("");
+        assert!(config.nvim_bin_path.is_none());
+        assert!(config.nvim_args.is_none());
+        assert!(config.timeout.is_none());
+        assert!(config.enable_swap.is_none());
+        assert!(config.server.is_none());
+        assert!(config.fork.is_none());
+    }
+}
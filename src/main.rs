@@ -41,6 +41,7 @@ extern crate toml;
 mod sys;
 
 mod color;
+mod config;
 mod dirs;
 mod mode;
 mod nvim_config;
@@ -60,6 +61,7 @@ mod plug_manager;
 mod popup_menu;
 mod project;
 mod render;
+mod session;
 mod settings;
 mod shell;
 mod shell_dlg;
@@ -78,16 +80,27 @@ use unix_daemonize::{daemonize_redirect, ChdirMode};
 use ui::Ui;
 
 use clap::{App, Arg, ArgMatches};
+use config::Config;
 use shell::ShellOptions;
 
+thread_local! {
+    /// This process's most recently created `Ui`. Kept around so that:
+    /// - in single-instance mode, a second D-Bus-forwarded `open()` call
+    ///   can hand its files to the existing Neovim instance instead of
+    ///   spawning a new window;
+    /// - on shutdown, we can ask the live Neovim instance which buffers
+    ///   are actually open, instead of persisting a stale launch-time
+    ///   file list.
+    static CURRENT_UI: RefCell<Option<Ui>> = RefCell::new(None);
+}
+
 const TIMEOUT_ARG: &str = "--timeout";
 const DISABLE_WIN_STATE_RESTORE: &str = "--disable-win-restore";
 const NO_FORK: &str = "--no-fork";
+const SERVER_ARG: &str = "server";
 
-fn main() {
-    env_logger::init();
-
-    let matches = App::new("NeovimGtk")
+fn build_cli() -> App<'static, 'static> {
+    App::new("NeovimGtk")
         .version(env!("CARGO_PKG_VERSION"))
         .author(env!("CARGO_PKG_AUTHORS"))
         .arg(
@@ -105,18 +118,58 @@ fn main() {
                 .help("Args will be passed to nvim")
                 .last(true)
                 .multiple(true),
-        ).get_matches();
+        ).arg(
+            Arg::with_name("restore-session")
+                .long("restore-session")
+                .help(
+                    "Restore previously open files (default when no files are \
+                     given); window layout is not restored",
+                )
+                .conflicts_with("no-restore-session"),
+        ).arg(
+            Arg::with_name("no-restore-session")
+                .long("no-restore-session")
+                .help("Don't restore the previous session"),
+        ).arg(
+            Arg::with_name("single-instance")
+                .long("single-instance")
+                .help(
+                    "Run as a single instance application: a second \
+                     invocation forwards its file list to the already \
+                     running instance instead of starting a new process",
+                ),
+        ).arg(
+            Arg::with_name(SERVER_ARG)
+                .long("server")
+                .value_name("ADDR")
+                .help(
+                    "Attach to an already running Neovim instance instead of \
+                     spawning a new one. ADDR can be a TCP address \
+                     (127.0.0.1:6666) or a unix socket path, mirroring \
+                     Neovim's --listen / $NVIM_LISTEN_ADDRESS; named pipes \
+                     are not supported",
+                ).takes_value(true),
+        )
+}
+
+fn main() {
+    env_logger::init();
+
+    let matches = build_cli().get_matches();
 
     let input_data = RefCell::new(read_piped_input());
 
+    let config = Config::load();
+
     #[cfg(unix)]
     {
         // fork to background by default
-        let want_fork = env::args()
+        let no_fork_arg = env::args()
             .take_while(|a| *a != "--")
             .skip(1)
             .find(|a| a.starts_with(NO_FORK))
-            .is_none();
+            .is_some();
+        let want_fork = !no_fork_arg && config.fork.unwrap_or(true);
 
         if want_fork {
             daemonize_redirect(
@@ -127,7 +180,14 @@ fn main() {
         }
     }
 
-    let app_flags = gio::ApplicationFlags::HANDLES_OPEN | gio::ApplicationFlags::NON_UNIQUE;
+    let app_flags = if matches.is_present("single-instance") {
+        // Rely on GApplication's D-Bus uniqueness: a second invocation will
+        // have its file list forwarded to the primary instance's
+        // `connect_open` handler instead of spawning a new process.
+        gio::ApplicationFlags::HANDLES_OPEN
+    } else {
+        gio::ApplicationFlags::HANDLES_OPEN | gio::ApplicationFlags::NON_UNIQUE
+    };
 
     glib::set_program_name(Some("NeovimGtk"));
 
@@ -138,17 +198,34 @@ fn main() {
     }.expect("Failed to initialize GTK application");
 
     let matches_copy = matches.clone();
-    app.connect_activate(move |app| activate(app, &matches_copy, input_data.replace(None)));
+    let config_copy = config.clone();
+    app.connect_activate(move |app| {
+        activate(app, &matches_copy, &config_copy, input_data.replace(None))
+    });
 
     let matches_copy = matches.clone();
-    app.connect_open(move |app, files, _| open(app, files, &matches_copy));
+    let config_copy = config.clone();
+    app.connect_open(move |app, files, _| open(app, files, &matches_copy, &config_copy));
 
     let app_ref = app.clone();
     let matches_copy = matches.clone();
+    let config_copy = config.clone();
     let new_window_action = gio::SimpleAction::new("new-window", None);
-    new_window_action.connect_activate(move |_, _| activate(&app_ref, &matches_copy, None));
+    new_window_action
+        .connect_activate(move |_, _| activate(&app_ref, &matches_copy, &config_copy, None));
     app.add_action(&new_window_action);
 
+    app.connect_shutdown(|_| {
+        let files = CURRENT_UI.with(|current| {
+            current
+                .borrow_mut()
+                .as_mut()
+                .map(|ui| ui.current_files())
+                .unwrap_or_default()
+        });
+        session::save(&files);
+    });
+
     gtk::Window::set_default_icon_name("org.daa.NeovimGtk");
 
     let app_exe = std::env::args().next().unwrap_or("nvim-gtk".to_owned());
@@ -165,43 +242,109 @@ fn main() {
     );
 }
 
-fn open(app: &gtk::Application, files: &[gio::File], matches: &ArgMatches) {
+fn open(app: &gtk::Application, files: &[gio::File], matches: &ArgMatches, config: &Config) {
     let files_list: Vec<String> = files
         .into_iter()
         .filter_map(|f| f.get_path()?.to_str().map(str::to_owned))
         .collect();
+
+    if matches.is_present("single-instance") && reuse_primary_window(&files_list) {
+        return;
+    }
+
     let mut ui = Ui::new(ShellOptions::new(
-        matches.value_of("nvim-bin-path").map(str::to_owned),
+        nvim_bin_path(matches, config),
         files_list,
-        nvim_timeout(std::env::args()),
-        matches
-            .values_of("nvim-args")
-            .map(|args| args.map(str::to_owned).collect())
-            .unwrap_or(vec![]),
+        nvim_timeout(std::env::args(), config),
+        nvim_args(matches, config),
         None,
-        matches.value_of("enable-swap").is_some(),
+        matches.value_of("enable-swap").is_some() || config.enable_swap.unwrap_or(false),
+        server_address(matches, config),
     ));
 
-    ui.init(app, !nvim_disable_win_state(std::env::args()));
+    ui.init(app, !nvim_disable_win_state(std::env::args()), false);
+
+    CURRENT_UI.with(|current| *current.borrow_mut() = Some(ui));
 }
 
-fn activate(app: &gtk::Application, matches: &ArgMatches, input_data: Option<String>) {
+/// If a primary `Ui` already exists in this process, open `files_list` as
+/// new tabs/buffers in it and raise its window, instead of creating a new
+/// window. Returns whether an existing `Ui` was found and reused.
+fn reuse_primary_window(files_list: &[String]) -> bool {
+    CURRENT_UI.with(|current| {
+        let mut current = current.borrow_mut();
+        match current.as_mut() {
+            Some(ui) => {
+                ui.open_files(files_list);
+                // Ask the Ui for its own window rather than guessing at
+                // `app.get_windows()[0]`, which isn't necessarily the one
+                // that just received these files once more than one
+                // window exists in this process.
+                if let Some(window) = ui.window() {
+                    window.present();
+                }
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+fn activate(
+    app: &gtk::Application,
+    matches: &ArgMatches,
+    config: &Config,
+    input_data: Option<String>,
+) {
+    let restore_session = want_restore_session(matches);
+    let restored_files = if restore_session {
+        session::load().map(|s| s.files).unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+
     let mut ui = Ui::new(ShellOptions::new(
-        matches.value_of("nvim-bin-path").map(str::to_owned),
-        Vec::new(),
-        nvim_timeout(std::env::args()),
-        matches
-            .values_of("nvim-args")
-            .map(|args| args.map(str::to_owned).collect())
-            .unwrap_or(vec![]),
+        nvim_bin_path(matches, config),
+        restored_files,
+        nvim_timeout(std::env::args(), config),
+        nvim_args(matches, config),
         input_data,
-        matches.value_of("enable-swap").is_some(),
+        matches.value_of("enable-swap").is_some() || config.enable_swap.unwrap_or(false),
+        server_address(matches, config),
     ));
 
-    ui.init(app, !nvim_disable_win_state(std::env::args()));
+    ui.init(
+        app,
+        !nvim_disable_win_state(std::env::args()),
+        restore_session,
+    );
+
+    CURRENT_UI.with(|current| *current.borrow_mut() = Some(ui));
+}
+
+fn nvim_bin_path(matches: &ArgMatches, config: &Config) -> Option<String> {
+    matches
+        .value_of("nvim-bin-path")
+        .map(str::to_owned)
+        .or_else(|| config.nvim_bin_path.clone())
+}
+
+fn nvim_args(matches: &ArgMatches, config: &Config) -> Vec<String> {
+    matches
+        .values_of("nvim-args")
+        .map(|args| args.map(str::to_owned).collect())
+        .or_else(|| config.nvim_args.clone())
+        .unwrap_or(vec![])
+}
+
+fn server_address(matches: &ArgMatches, config: &Config) -> Option<String> {
+    matches
+        .value_of(SERVER_ARG)
+        .map(str::to_owned)
+        .or_else(|| config.server.clone())
 }
 
-fn nvim_timeout<I>(mut args: I) -> Option<Duration>
+fn nvim_timeout<I>(mut args: I, config: &Config) -> Option<Duration>
 where
     I: Iterator<Item = String>,
 {
@@ -213,7 +356,18 @@ where
                 error!("Can't convert timeout argument to integer: {}", err);
                 None
             }
-        }).map(|timeout| Duration::from_secs(timeout))
+        }).or(config.timeout)
+        .map(Duration::from_secs)
+}
+
+fn want_restore_session(matches: &ArgMatches) -> bool {
+    if matches.is_present("no-restore-session") {
+        false
+    } else if matches.is_present("restore-session") {
+        true
+    } else {
+        !matches.is_present("files")
+    }
 }
 
 fn nvim_disable_win_state<I>(mut args: I) -> bool
@@ -240,3 +394,113 @@ fn read_piped_input() -> Option<String> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches_from(args: &[&str]) -> ArgMatches<'static> {
+        build_cli().get_matches_from_safe(args).unwrap()
+    }
+
+    fn config_with_server(server: &str) -> Config {
+        let mut config = Config::default();
+        config.nvim_bin_path = Some("/from/config/nvim".to_owned());
+        config.nvim_args = Some(vec!["--from-config".to_owned()]);
+        config.server = Some(server.to_owned());
+        config.timeout = Some(99);
+        config
+    }
+
+    #[test]
+    fn nvim_bin_path_cli_wins_over_config() {
+        let matches = matches_from(&["nvim-gtk", "--nvim-bin-path", "/cli/nvim"]);
+        let config = config_with_server("127.0.0.1:1");
+        assert_eq!(nvim_bin_path(&matches, &config), Some("/cli/nvim".to_owned()));
+    }
+
+    #[test]
+    fn nvim_bin_path_falls_back_to_config() {
+        let matches = matches_from(&["nvim-gtk"]);
+        let config = config_with_server("127.0.0.1:1");
+        assert_eq!(
+            nvim_bin_path(&matches, &config),
+            Some("/from/config/nvim".to_owned())
+        );
+    }
+
+    #[test]
+    fn nvim_args_cli_wins_over_config() {
+        let matches = matches_from(&["nvim-gtk", "--", "--clean"]);
+        let config = config_with_server("127.0.0.1:1");
+        assert_eq!(nvim_args(&matches, &config), vec!["--clean".to_owned()]);
+    }
+
+    #[test]
+    fn nvim_args_falls_back_to_config() {
+        let matches = matches_from(&["nvim-gtk"]);
+        let config = config_with_server("127.0.0.1:1");
+        assert_eq!(
+            nvim_args(&matches, &config),
+            vec!["--from-config".to_owned()]
+        );
+    }
+
+    #[test]
+    fn server_address_cli_wins_over_config() {
+        let matches = matches_from(&["nvim-gtk", "--server", "127.0.0.1:6666"]);
+        let config = config_with_server("/tmp/from-config.sock");
+        assert_eq!(
+            server_address(&matches, &config),
+            Some("127.0.0.1:6666".to_owned())
+        );
+    }
+
+    #[test]
+    fn server_address_falls_back_to_config() {
+        let matches = matches_from(&["nvim-gtk"]);
+        let config = config_with_server("/tmp/from-config.sock");
+        assert_eq!(
+            server_address(&matches, &config),
+            Some("/tmp/from-config.sock".to_owned())
+        );
+    }
+
+    #[test]
+    fn nvim_timeout_cli_wins_over_config() {
+        let config = config_with_server("127.0.0.1:1");
+        let args = vec!["--timeout=5".to_owned()].into_iter();
+        assert_eq!(nvim_timeout(args, &config), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn nvim_timeout_falls_back_to_config() {
+        let config = config_with_server("127.0.0.1:1");
+        let args = Vec::<String>::new().into_iter();
+        assert_eq!(nvim_timeout(args, &config), Some(Duration::from_secs(99)));
+    }
+
+    #[test]
+    fn restores_by_default_when_no_files() {
+        let matches = matches_from(&["nvim-gtk"]);
+        assert!(want_restore_session(&matches));
+    }
+
+    #[test]
+    fn does_not_restore_by_default_when_files_given() {
+        let matches = matches_from(&["nvim-gtk", "foo.txt"]);
+        assert!(!want_restore_session(&matches));
+    }
+
+    #[test]
+    fn no_restore_session_flag_always_wins() {
+        let matches = matches_from(&["nvim-gtk", "--no-restore-session"]);
+        assert!(!want_restore_session(&matches));
+    }
+
+    #[test]
+    fn restore_session_flag_forces_restore_even_with_files() {
+        let matches = matches_from(&["nvim-gtk", "--restore-session", "foo.txt"]);
+        assert!(want_restore_session(&matches));
+    }
+}
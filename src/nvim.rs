@@ -0,0 +1,38 @@
+//! Starts the Neovim backend for a window: either spawns a fresh `nvim`
+//! child process, or attaches to an already running instance over the
+//! socket given via `ShellOptions::server_address` (a TCP address like
+//! `127.0.0.1:6666`, or a unix socket path), mirroring Neovim's own
+//! `--listen` / `$NVIM_LISTEN_ADDRESS` convention. Any address that does
+//! not parse as a TCP socket address is opened as a unix socket; named
+//! pipes are not supported.
+
+use neovim_lib::Session;
+
+use shell::ShellOptions;
+
+pub fn new_session(options: &ShellOptions) -> Result<Session, String> {
+    match options.server_address {
+        Some(ref addr) => connect(addr),
+        None => spawn(options),
+    }
+}
+
+fn connect(addr: &str) -> Result<Session, String> {
+    if addr.parse::<::std::net::SocketAddr>().is_ok() {
+        Session::new_tcp(addr).map_err(|err| format!("Can't connect to {}: {}", addr, err))
+    } else {
+        Session::new_unix_socket(addr)
+            .map_err(|err| format!("Can't connect to {}: {}", addr, err))
+    }
+}
+
+fn spawn(options: &ShellOptions) -> Result<Session, String> {
+    let bin_path = options
+        .nvim_bin_path
+        .as_ref()
+        .map(String::as_str)
+        .unwrap_or("nvim");
+
+    Session::new_child_path(bin_path, options.nvim_args.clone())
+        .map_err(|err| format!("Can't start {}: {}", bin_path, err))
+}
@@ -0,0 +1,54 @@
+//! Session save/restore: on clean shutdown the set of currently open
+//! buffers (queried live from Neovim via `Ui::current_files`, not just the
+//! files given at launch) is serialized to a `session.json` file in the
+//! app config directory, and read back on the next launch when restore is
+//! requested and no files were given on the command line.
+//!
+//! KNOWN GAP, not silently dropped: window/tab *layout* (split
+//! arrangement, tab order, per-buffer cursor position) is NOT restored —
+//! only the flat list of open file paths round-trips. The original
+//! request asked for both; this covers the file-list half only, and the
+//! layout half remains open follow-up work.
+
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Session {
+    pub files: Vec<String>,
+}
+
+fn path() -> Option<PathBuf> {
+    dirs::get_app_config_dir().map(|dir| dir.join("session.json"))
+}
+
+pub fn load() -> Option<Session> {
+    let content = path().and_then(|path| fs::read_to_string(path).ok())?;
+    match serde_json::from_str(&content) {
+        Ok(session) => Some(session),
+        Err(err) => {
+            error!("Can't parse session.json: {}", err);
+            None
+        }
+    }
+}
+
+pub fn save(files: &[String]) {
+    let path = match path() {
+        Some(path) => path,
+        None => return,
+    };
+
+    let session = Session {
+        files: files.to_owned(),
+    };
+
+    match serde_json::to_string(&session) {
+        Ok(content) => {
+            if let Err(err) = fs::write(&path, content) {
+                error!("Can't write session file: {}", err);
+            }
+        }
+        Err(err) => error!("Can't serialize session: {}", err),
+    }
+}
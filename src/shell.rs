@@ -0,0 +1,37 @@
+//! Options controlling how a window's Neovim instance is launched: either
+//! spawned as a child process or attached to over a socket. See the `nvim`
+//! module for the code that actually acts on `server_address`.
+
+use std::time::Duration;
+
+pub struct ShellOptions {
+    pub nvim_bin_path: Option<String>,
+    pub open_files: Vec<String>,
+    pub timeout: Option<Duration>,
+    pub nvim_args: Vec<String>,
+    pub input_data: Option<String>,
+    pub enable_swap: bool,
+    pub server_address: Option<String>,
+}
+
+impl ShellOptions {
+    pub fn new(
+        nvim_bin_path: Option<String>,
+        open_files: Vec<String>,
+        timeout: Option<Duration>,
+        nvim_args: Vec<String>,
+        input_data: Option<String>,
+        enable_swap: bool,
+        server_address: Option<String>,
+    ) -> ShellOptions {
+        ShellOptions {
+            nvim_bin_path,
+            open_files,
+            timeout,
+            nvim_args,
+            input_data,
+            enable_swap,
+            server_address,
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! Owns a single window's GTK chrome and its Neovim RPC session. `new()`
+//! just stores the launch options; `init()` creates the window and starts
+//! (or attaches to) Neovim via the `nvim` module.
+
+use gtk;
+use gtk::prelude::*;
+use neovim_lib::{Neovim, NeovimApi};
+
+use nvim;
+use shell::ShellOptions;
+
+pub struct Ui {
+    options: ShellOptions,
+    window: Option<gtk::ApplicationWindow>,
+    nvim: Option<Neovim>,
+}
+
+impl Ui {
+    pub fn new(options: ShellOptions) -> Ui {
+        Ui {
+            options,
+            window: None,
+            nvim: None,
+        }
+    }
+
+    pub fn init(&mut self, app: &gtk::Application, restore_win_state: bool, restore_session: bool) {
+        let window = gtk::ApplicationWindow::new(app);
+        window.set_title("NeovimGtk");
+        if !restore_win_state {
+            window.set_default_size(800, 600);
+        }
+
+        match nvim::new_session(&self.options) {
+            Ok(session) => {
+                let mut nvim = Neovim::new(session);
+                nvim.session.start_event_loop();
+
+                for file in self.options.open_files.clone() {
+                    if let Err(err) = nvim.command(&format!("edit {}", file)) {
+                        error!("Can't open {}: {}", file, err);
+                    }
+                }
+
+                self.nvim = Some(nvim);
+            }
+            Err(err) => error!("Can't start nvim: {}", err),
+        }
+
+        let _ = restore_session; // caller already decided the initial file list
+
+        window.show_all();
+        self.window = Some(window);
+    }
+
+    /// Open `files` as new tabs in this window's running Neovim instance.
+    pub fn open_files(&mut self, files: &[String]) {
+        let nvim = match self.nvim.as_mut() {
+            Some(nvim) => nvim,
+            None => return,
+        };
+
+        for file in files {
+            if let Err(err) = nvim.command(&format!("tabnew {}", file)) {
+                error!("Can't open {}: {}", file, err);
+            }
+        }
+    }
+
+    pub fn window(&self) -> Option<&gtk::ApplicationWindow> {
+        self.window.as_ref()
+    }
+
+    /// The file paths currently backing open buffers, queried live from
+    /// Neovim. Used to persist an accurate session on shutdown.
+    pub fn current_files(&mut self) -> Vec<String> {
+        let nvim = match self.nvim.as_mut() {
+            Some(nvim) => nvim,
+            None => return Vec::new(),
+        };
+
+        let bufs = match nvim.list_bufs() {
+            Ok(bufs) => bufs,
+            Err(err) => {
+                error!("Can't list buffers: {}", err);
+                return Vec::new();
+            }
+        };
+
+        bufs.into_iter()
+            .filter_map(|buf| buf.get_name(nvim).ok())
+            .filter(|name| !name.is_empty())
+            .collect()
+    }
+}